@@ -0,0 +1,114 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! On-disk cache of the collected post history, so later runs only have to
+//! walk the commits added since.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use git2::Oid;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::feed::AuthorCtx;
+use crate::history::{Merges, PostHistory, Time};
+use crate::output::write_atomic;
+
+/// Bumped whenever the layout of the cache changes.
+const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct CachedPost {
+	origin: Option<(String, String)>,
+	initial: (i64, i32),
+	latest: (i64, i32),
+	author: AuthorCtx,
+	contributors: Vec<AuthorCtx>
+}
+impl From<&PostHistory> for CachedPost {
+	fn from(history: &PostHistory) -> Self {
+		Self {
+			origin: history.origin.as_ref().map(|(commit, path)| (commit.to_string(), path.clone())),
+			initial: (history.initial.seconds(), history.initial.offset_minutes()),
+			latest: (history.latest.seconds(), history.latest.offset_minutes()),
+			author: history.author.clone(),
+			contributors: history.contributors.clone()
+		}
+	}
+}
+impl CachedPost {
+	fn into_history(self) -> Option<PostHistory> {
+		let origin = match self.origin {
+			Some((commit, path)) => Some((Oid::from_str(&commit).ok()?, path)),
+			None => None
+		};
+		Some(PostHistory {
+			origin,
+			initial: Time::new(self.initial.0, self.initial.1),
+			latest: Time::new(self.latest.0, self.latest.1),
+			author: self.author,
+			contributors: self.contributors
+		})
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cache {
+	version: u32,
+	content_dir: PathBuf,
+	merges: String,
+	head: String,
+	posts: HashMap<PathBuf, CachedPost>
+}
+
+fn merges_key(merges: Merges) -> &'static str {
+	match merges {
+		Merges::FirstParent => "first-parent",
+		Merges::Skip => "skip"
+	}
+}
+
+/// Reads the cache at `path`, returning the commit it was written for and
+/// the history up to it. Missing, unreadable or mismatching caches yield
+/// `None`, they are simply rebuilt.
+pub(crate) fn load(path: &Path, content_dir: &Path, merges: Merges) -> Option<(Oid, HashMap<PathBuf, PostHistory>)> {
+	let raw = std::fs::read(path).ok()?;
+	let cache: Cache = serde_json::from_slice(&raw).ok()?;
+	if cache.version != VERSION || cache.content_dir != content_dir || cache.merges != merges_key(merges) {
+		return None;
+	}
+	let head = Oid::from_str(&cache.head).ok()?;
+	let posts = cache.posts.into_iter()
+		.map(|(path, post)| Some((path, post.into_history()?)))
+		.collect::<Option<HashMap<_, _>>>()?;
+	Some((head, posts))
+}
+
+/// Writes the history up to `head` to the cache at `path`.
+pub(crate) fn store(path: &Path, content_dir: &Path, merges: Merges, head: Oid, records: &HashMap<PathBuf, PostHistory>) -> Result<()> {
+	let cache = Cache {
+		version: VERSION,
+		content_dir: content_dir.to_path_buf(),
+		merges: merges_key(merges).to_string(),
+		head: head.to_string(),
+		posts: records.iter().map(|(path, history)| (path.clone(), CachedPost::from(history))).collect()
+	};
+	write_atomic(path, &serde_json::to_string(&cache)?)
+}
@@ -0,0 +1,126 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The per-site `gitfeet.toml` configuration file.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::feed::{AuthorInfo, FeedBuilder, FeedMeta, Format, IdScheme, Order, TitleStyle, Untracked};
+use crate::history::Merges;
+
+/// Name of the configuration file looked up in the repository root.
+pub const FILE_NAME: &str = "gitfeet.toml";
+
+/// Per-site settings read from `gitfeet.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+	pub content: Option<String>,
+	pub format: Option<Format>,
+	pub template: Option<PathBuf>,
+	pub output: Option<PathBuf>,
+	/// Feeds written from a single history walk, in place of `format`,
+	/// `template` and `output`.
+	pub outputs: Vec<Output>,
+	/// Leave output files untouched if only the `updated` timestamp changed.
+	pub skip_unchanged: bool,
+	pub max_entries: Option<usize>,
+	pub order: Option<Order>,
+	pub base_url: Option<String>,
+	pub id_scheme: Option<IdScheme>,
+	pub tag_authority: Option<String>,
+	pub merges: Option<Merges>,
+	pub untracked: Option<Untracked>,
+	pub title_style: Option<TitleStyle>,
+	/// Globs selecting the posts, relative to the repository root.
+	pub include: Vec<String>,
+	pub exclude: Vec<String>,
+	/// History cache, relative to the repository root.
+	pub cache: Option<PathBuf>,
+	#[serde(rename = "ref")]
+	pub reference: Option<String>,
+	pub feed: FeedMeta,
+	/// Presentation of authors, keyed by email.
+	pub authors: HashMap<String, AuthorInfo>
+}
+
+/// A single feed written by a run, configured through `[[outputs]]` tables.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Output {
+	pub format: Format,
+	pub path: PathBuf,
+	#[serde(default)]
+	pub template: Option<PathBuf>
+}
+
+impl Config {
+	pub fn load(path: &Path) -> Result<Self> {
+		let raw = std::fs::read_to_string(path).map_err(Error::io(path))?;
+		Self::parse(&raw, path)
+	}
+	/// Parses the contents of a config file, `path` is only used for error reporting.
+	pub fn parse(raw: &str, path: &Path) -> Result<Self> {
+		toml::from_str(raw).map_err(|source| Error::Config { path: path.to_path_buf(), source })
+	}
+
+	/// Applies every setting present in the file to `builder`.
+	pub fn apply<'r>(&self, mut builder: FeedBuilder<'r>) -> FeedBuilder<'r> {
+		if let Some(content) = &self.content {
+			builder = builder.content_dir(content);
+		}
+		if !self.include.is_empty() {
+			builder = builder.include(&self.include);
+		}
+		if !self.exclude.is_empty() {
+			builder = builder.exclude(&self.exclude);
+		}
+		if let Some(max_entries) = self.max_entries {
+			builder = builder.max_entries(max_entries);
+		}
+		if let Some(order) = self.order {
+			builder = builder.order(order);
+		}
+		if let Some(base_url) = &self.base_url {
+			builder = builder.base_url(base_url);
+		}
+		if let Some(id_scheme) = self.id_scheme {
+			builder = builder.id_scheme(id_scheme);
+		}
+		if let Some(authority) = &self.tag_authority {
+			builder = builder.tag_authority(authority);
+		}
+		if let Some(merges) = self.merges {
+			builder = builder.merges(merges);
+		}
+		if let Some(untracked) = self.untracked {
+			builder = builder.untracked(untracked);
+		}
+		if let Some(style) = self.title_style {
+			builder = builder.title_style(style);
+		}
+		if let Some(reference) = &self.reference {
+			builder = builder.reference(reference);
+		}
+		builder.authors(self.authors.clone()).meta(self.feed.clone())
+	}
+}
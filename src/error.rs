@@ -0,0 +1,81 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+	#[error(transparent)]
+	Git(#[from] git2::Error),
+	#[error("failed to access {}: {source}", path.display())]
+	Io {
+		path: PathBuf,
+		source: io::Error
+	},
+	#[error(transparent)]
+	Template(#[from] tinytemplate::error::Error),
+	#[error("failed to parse {}: {source}", path.display())]
+	Config {
+		path: PathBuf,
+		source: toml::de::Error
+	},
+	#[error("repository has no working directory")]
+	NoWorkdir,
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+	#[error(transparent)]
+	Glob(#[from] globset::Error),
+	#[error("unknown output format `{0}`")]
+	UnknownFormat(String),
+	#[error("unknown id scheme `{0}`")]
+	UnknownIdScheme(String),
+	#[error("unknown merge mode `{0}`")]
+	UnknownMergeMode(String),
+	#[error("unknown policy for untracked posts `{0}`")]
+	UnknownUntrackedPolicy(String),
+	#[error("invalid front matter in {path}: {message}")]
+	FrontMatter {
+		path: String,
+		message: String
+	},
+	#[error("unknown entry order `{0}`")]
+	UnknownOrder(String),
+	#[error("unknown title style `{0}`")]
+	UnknownTitleStyle(String),
+	#[error("{0} does not follow the `NNN.Title.md` naming convention")]
+	InvalidPostName(String),
+	#[error("{0} has no history")]
+	Untracked(String),
+	#[error("{0} has not been committed and no default author is configured")]
+	NoDefaultAuthor(String),
+	#[error("malformed ref update `{0}`, expected `<old> <new> <ref>`")]
+	InvalidRefUpdate(String)
+}
+
+impl Error {
+	pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+		let path = path.into();
+		move |source| Self::Io { path, source }
+	}
+}
@@ -0,0 +1,266 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Metadata block at the top of a post, either YAML delimited by `---` or
+//! TOML delimited by `+++`.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::feed::AuthorCtx;
+
+/// An author as written in front matter, either `Name <email>` or a table.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawAuthor {
+	Short(String),
+	Full {
+		name: String,
+		#[serde(default)]
+		email: String,
+		#[serde(default)]
+		uri: Option<String>
+	}
+}
+impl From<RawAuthor> for AuthorCtx {
+	fn from(author: RawAuthor) -> Self {
+		match author {
+			RawAuthor::Short(author) => match author.split_once('<') {
+				Some((name, email)) => Self {
+					name: name.trim().to_string(),
+					email: email.trim_end().trim_end_matches('>').to_string(),
+					uri: None
+				},
+				None => Self {
+					name: author.trim().to_string(),
+					email: String::new(),
+					uri: None
+				}
+			},
+			RawAuthor::Full { name, email, uri } => Self { name, email, uri }
+		}
+	}
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Raw {
+	title: Option<String>,
+	date: Option<String>,
+	updated: Option<String>,
+	author: Option<RawAuthor>,
+	authors: Vec<RawAuthor>,
+	tags: Vec<String>,
+	summary: Option<String>,
+	draft: bool,
+	id: Option<String>,
+	lang: Option<String>
+}
+
+/// Post metadata overriding what is derived from the file name and history.
+#[derive(Debug, Default)]
+pub(crate) struct FrontMatter {
+	pub title: Option<String>,
+	pub date: Option<DateTime<FixedOffset>>,
+	pub updated: Option<DateTime<FixedOffset>>,
+	pub authors: Vec<AuthorCtx>,
+	pub tags: Vec<String>,
+	pub summary: Option<String>,
+	pub draft: bool,
+	pub id: Option<String>,
+	pub lang: Option<String>
+}
+impl FrontMatter {
+	/// Splits the front matter off `content`, returning it together with
+	/// the remaining markdown. Posts without front matter are returned as is.
+	///
+	/// A `---` block only counts as front matter if it holds a YAML mapping,
+	/// otherwise the delimiters are taken for markdown thematic breaks.
+	pub fn parse<'c>(content: &'c str, path: &str) -> Result<(Self, &'c str)> {
+		let (delimiter, raw, body) = match split(content) {
+			Some(parts) => parts,
+			None => return Ok((Self::default(), content))
+		};
+		let invalid = |message: String| Error::FrontMatter { path: path.to_string(), message };
+		let raw: Raw = match delimiter {
+			_ if raw.trim().is_empty() => Raw::default(),
+			YAML => match serde_yaml::from_str(raw) {
+				Ok(value @ serde_yaml::Value::Mapping(_)) => serde_yaml::from_value(value).map_err(|err| invalid(err.to_string()))?,
+				_ => return Ok((Self::default(), content))
+			},
+			_ => raw.parse::<toml::Value>()
+				.and_then(|value| stringify_dates(value).try_into())
+				.map_err(|err| invalid(err.to_string()))?
+		};
+		let date = |date: Option<String>| date.map(|date| parse_date(&date).ok_or_else(|| invalid(format!("`{}` is not a date", date)))).transpose();
+		Ok((Self {
+			title: raw.title,
+			date: date(raw.date)?,
+			updated: date(raw.updated)?,
+			authors: raw.author.into_iter().chain(raw.authors).map(AuthorCtx::from).collect(),
+			tags: raw.tags,
+			summary: raw.summary,
+			draft: raw.draft,
+			id: raw.id,
+			lang: raw.lang
+		}, body))
+	}
+}
+
+const YAML: &str = "---";
+const TOML: &str = "+++";
+
+/// Returns the delimiter, the front matter and the body following it.
+fn split(content: &str) -> Option<(&'static str, &str, &str)> {
+	let first = content.lines().next()?.trim_end();
+	let delimiter = [YAML, TOML].iter().find(|delimiter| **delimiter == first)?;
+	let start = content.find('\n')? + 1;
+	let mut end = start;
+	for line in content[start..].split_inclusive('\n') {
+		if line.trim_end() == *delimiter {
+			return Some((delimiter, &content[start..end], &content[end + line.len()..]));
+		}
+		end += line.len();
+	}
+	None
+}
+
+/// TOML has a native datetime type, turn those into strings like YAML has them.
+fn stringify_dates(value: toml::Value) -> toml::Value {
+	match value {
+		toml::Value::Datetime(date) => toml::Value::String(date.to_string()),
+		toml::Value::Array(values) => toml::Value::Array(values.into_iter().map(stringify_dates).collect()),
+		toml::Value::Table(table) => toml::Value::Table(table.into_iter().map(|(key, value)| (key, stringify_dates(value))).collect()),
+		value => value
+	}
+}
+
+/// Accepts RFC 3339 timestamps, and dates or times without offset as UTC.
+fn parse_date(date: &str) -> Option<DateTime<FixedOffset>> {
+	let utc = FixedOffset::east(0);
+	DateTime::parse_from_rfc3339(date).ok()
+		.or_else(|| NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").ok().map(|date| utc.from_utc_datetime(&date)))
+		.or_else(|| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").ok().map(|date| utc.from_utc_datetime(&date)))
+		.or_else(|| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().map(|date| utc.from_utc_datetime(&date.and_hms(0, 0, 0))))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn yaml() {
+		let post = "---\ntitle: Hello\ndate: 2021-08-01\nauthor: Alice <alice@example.org>\ntags: [a, b]\ndraft: true\n---\n# Body\n";
+		let (meta, body) = FrontMatter::parse(post, "001.Hello.md").unwrap();
+		assert_eq!(meta.title.as_deref(), Some("Hello"));
+		assert_eq!(meta.date.unwrap().to_rfc3339(), "2021-08-01T00:00:00+00:00");
+		assert_eq!(meta.authors[0].name, "Alice");
+		assert_eq!(meta.authors[0].email, "alice@example.org");
+		assert_eq!(meta.tags, ["a", "b"]);
+		assert!(meta.draft);
+		assert_eq!(body, "# Body\n");
+	}
+
+	#[test]
+	fn toml() {
+		let post = "+++\ntitle = \"Hello\"\ndate = 2021-08-01T12:30:00+02:00\nupdated = 2021-08-02\n[[authors]]\nname = \"Bob\"\nuri = \"https://bob.example.org\"\n+++\nBody\n";
+		let (meta, body) = FrontMatter::parse(post, "001.Hello.md").unwrap();
+		assert_eq!(meta.title.as_deref(), Some("Hello"));
+		assert_eq!(meta.date.unwrap().to_rfc3339(), "2021-08-01T12:30:00+02:00");
+		assert_eq!(meta.updated.unwrap().to_rfc3339(), "2021-08-02T00:00:00+00:00");
+		assert_eq!(meta.authors[0].name, "Bob");
+		assert_eq!(meta.authors[0].email, "");
+		assert_eq!(meta.authors[0].uri.as_deref(), Some("https://bob.example.org"));
+		assert_eq!(body, "Body\n");
+	}
+
+	#[test]
+	fn crlf() {
+		let (meta, body) = FrontMatter::parse("---\r\ntitle: Hello\r\n---\r\nBody\r\n", "001.Hello.md").unwrap();
+		assert_eq!(meta.title.as_deref(), Some("Hello"));
+		assert_eq!(body, "Body\r\n");
+	}
+
+	#[test]
+	fn unterminated() {
+		let post = "---\ntitle: Hello\nBody\n";
+		let (meta, body) = FrontMatter::parse(post, "001.Hello.md").unwrap();
+		assert!(meta.title.is_none());
+		assert_eq!(body, post);
+	}
+
+	#[test]
+	fn empty() {
+		for post in &["---\n---\nBody\n", "+++\n+++\nBody\n"] {
+			let (meta, body) = FrontMatter::parse(post, "001.Hello.md").unwrap();
+			assert!(meta.title.is_none());
+			assert_eq!(body, "Body\n");
+		}
+	}
+
+	#[test]
+	fn thematic_break() {
+		let post = "---\nSome text between two rules.\n---\nMore text\n";
+		let (meta, body) = FrontMatter::parse(post, "001.Hello.md").unwrap();
+		assert!(meta.title.is_none());
+		assert_eq!(body, post);
+	}
+
+	#[test]
+	fn invalid() {
+		assert!(FrontMatter::parse("---\ndate: yesterday\n---\n", "001.Hello.md").is_err());
+		assert!(FrontMatter::parse("+++\ntitle = \n+++\n", "001.Hello.md").is_err());
+	}
+
+	#[test]
+	fn authors() {
+		let author = |raw: &str| AuthorCtx::from(RawAuthor::Short(raw.to_string()));
+		let alice = author("Alice Example <alice@example.org>");
+		assert_eq!((alice.name.as_str(), alice.email.as_str()), ("Alice Example", "alice@example.org"));
+		let bob = author(" Bob ");
+		assert_eq!((bob.name.as_str(), bob.email.as_str()), ("Bob", ""));
+	}
+
+	#[test]
+	fn split_delimiters() {
+		assert_eq!(split("+++\na = 1\n+++\nrest"), Some((TOML, "a = 1\n", "rest")));
+		assert_eq!(split("---\na: 1\n---"), Some((YAML, "a: 1\n", "")));
+		assert_eq!(split("# Title\n---\n"), None);
+		assert_eq!(split("----\na: 1\n----\n"), None);
+	}
+
+	#[test]
+	fn toml_dates() {
+		let value: toml::Value = "a = 1979-05-27\nb = [1979-05-27T07:32:00Z]".parse().unwrap();
+		let value = stringify_dates(value);
+		assert_eq!(value["a"].as_str(), Some("1979-05-27"));
+		assert_eq!(value["b"][0].as_str(), Some("1979-05-27T07:32:00Z"));
+	}
+
+	#[test]
+	fn dates() {
+		let date = |raw| parse_date(raw).map(|date| date.to_rfc3339());
+		assert_eq!(date("2021-08-01T12:00:00+02:00").as_deref(), Some("2021-08-01T12:00:00+02:00"));
+		assert_eq!(date("2021-08-01T12:00:00").as_deref(), Some("2021-08-01T12:00:00+00:00"));
+		assert_eq!(date("2021-08-01 12:00:00").as_deref(), Some("2021-08-01T12:00:00+00:00"));
+		assert_eq!(date("2021-08-01").as_deref(), Some("2021-08-01T00:00:00+00:00"));
+		assert_eq!(date("01.08.2021"), None);
+	}
+}
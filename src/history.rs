@@ -0,0 +1,597 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read_dir;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone, FixedOffset};
+use serde::Deserialize;
+use git2::{self as git, Commit, Delta, DiffFindOptions, DiffOptions, ObjectType, Oid, Repository, Sort, Tree, TreeWalkMode, TreeWalkResult};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::cache;
+use crate::error::{Error, Result};
+use crate::feed::AuthorCtx;
+
+#[derive(Clone, Copy)]
+pub struct Time(git::Time);
+impl Time {
+	pub fn new(seconds: i64, offset_minutes: i32) -> Self {
+		Self(git::Time::new(seconds, offset_minutes))
+	}
+	pub fn seconds(self) -> i64 {
+		self.0.seconds()
+	}
+	pub fn offset_minutes(self) -> i32 {
+		self.0.offset_minutes()
+	}
+	pub fn to_chrono(self) -> DateTime<FixedOffset> {
+		FixedOffset::east(self.0.offset_minutes() * 60).timestamp(self.0.seconds(), 0)
+	}
+}
+impl fmt::Debug for Time {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_fmt(format_args!("Time {}", self.0.seconds()))
+	}
+}
+
+/// When and by whom a post was published and last touched.
+#[derive(Debug, Clone)]
+pub struct PostHistory {
+	/// The commit that introduced the post, together with the path it had
+	/// back then. `None` for posts that were never committed.
+	pub origin: Option<(Oid, String)>,
+	pub initial: Time,
+	pub latest: Time,
+	/// Author of the commit that introduced the post.
+	pub author: AuthorCtx,
+	/// Everyone else who later edited the post, in the order of their first edit.
+	pub contributors: Vec<AuthorCtx>
+}
+
+/// A post file name following the vueBlog `NNN.Title.md` convention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostName {
+	/// The numeric prefix, posts are ordered by it.
+	pub index: u64,
+	/// Everything between the prefix and the extension, dots included.
+	pub title: String,
+	/// Whether the name is marked as draft, as in `NNN.Title.draft.md`.
+	pub draft: bool
+}
+impl PostName {
+	pub fn parse(path: &str) -> Result<Self> {
+		let invalid = || Error::InvalidPostName(path.to_string());
+		let stem = Path::new(path).file_stem().ok_or_else(invalid)?.to_string_lossy();
+		let (index, title) = stem.split_once('.').ok_or_else(invalid)?;
+		if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) || title.is_empty() {
+			return Err(invalid());
+		}
+		let (title, draft) = match title.strip_suffix(".draft") {
+			Some(title) => (title, true),
+			None => (title, false)
+		};
+		Ok(Self {
+			index: index.parse().map_err(|_| invalid())?,
+			title: title.to_string(),
+			draft
+		})
+	}
+}
+
+/// A single post and the times and author of the commits that touched it.
+#[derive(Debug)]
+pub struct BlogPost {
+	path: String,
+	history: Option<PostHistory>
+}
+impl BlogPost {
+	/// Path of the post relative to the repository root.
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+	/// The history of the post, `None` if no commit ever touched it.
+	pub fn history(&self) -> Option<&PostHistory> {
+		self.history.as_ref()
+	}
+	pub fn set_history(&mut self, history: PostHistory) {
+		self.history = Some(history);
+	}
+	/// Parses the file name of the post.
+	pub fn name(&self) -> Result<PostName> {
+		PostName::parse(&self.path)
+	}
+}
+
+/// How merge commits are treated while walking the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Merges {
+	/// Diff merges against their first parent. Posts touched by a merge are
+	/// updated at the time it landed, but only the authors of the commits on
+	/// the merged branch count as contributors.
+	FirstParent,
+	/// Ignore merge commits entirely.
+	Skip
+}
+impl FromStr for Merges {
+	type Err = Error;
+	fn from_str(s: &str) -> Result<Self> {
+		match s {
+			"first-parent" => Ok(Self::FirstParent),
+			"skip" => Ok(Self::Skip),
+			_ => Err(Error::UnknownMergeMode(s.to_string()))
+		}
+	}
+}
+
+/// Decides which files below the content directory are posts, by matching
+/// their repository-relative paths against include and exclude globs.
+#[derive(Debug)]
+pub struct PostFilter {
+	include: GlobSet,
+	exclude: GlobSet
+}
+impl PostFilter {
+	/// Without `include` patterns, every markdown file below `content_dir`
+	/// named like `NNN.Title.md` is a post, so notes such as a `README.md`
+	/// can live next to the posts.
+	pub fn new(content_dir: &Path, include: &[String], exclude: &[String]) -> Result<Self> {
+		let default = [content_dir.join("**").join("[0-9]*.*.md").to_string_lossy().to_string()];
+		let include = if include.is_empty() { &default[..] } else { include };
+		Ok(Self {
+			include: glob_set(include)?,
+			exclude: glob_set(exclude)?
+		})
+	}
+	pub fn matches(&self, path: &str) -> bool {
+		self.include.is_match(path) && !self.exclude.is_match(path)
+	}
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+	let mut set = GlobSetBuilder::new();
+	for pattern in patterns {
+		set.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+	}
+	Ok(set.build()?)
+}
+
+/// All posts of a repository, keyed by their repository-relative path.
+#[derive(Debug, Default)]
+pub struct BlogPosts(BTreeMap<String, BlogPost>);
+impl BlogPosts {
+	pub fn new() -> Self {
+		Self(BTreeMap::new())
+	}
+	/// Lists every file below `content_dir` of `tree` that passes `filter` as a post.
+	pub fn from_tree(repo: &Repository, tree: &Tree, content_dir: &Path, filter: &PostFilter) -> Result<Self> {
+		// An empty content directory is the repository root
+		let dir = if content_dir.as_os_str().is_empty() {
+			tree.clone()
+		} else {
+			tree.get_path(content_dir)?.to_object(repo)?.peel_to_tree()?
+		};
+		let mut posts = Self::new();
+		dir.walk(TreeWalkMode::PreOrder, |parent, entry| {
+			if entry.kind() == Some(ObjectType::Blob) {
+				let path = content_dir.join(parent).join(String::from_utf8_lossy(entry.name_bytes()).as_ref()).to_string_lossy().to_string();
+				if filter.matches(&path) {
+					posts.insert(path);
+				}
+			}
+			TreeWalkResult::Ok
+		})?;
+		Ok(posts)
+	}
+	/// Lists every file below `content_dir` (relative to `root`) that passes
+	/// `filter` as a post.
+	pub fn from_dir(root: &Path, content_dir: &Path, filter: &PostFilter) -> Result<Self> {
+		let mut posts = Self::new();
+		let mut dirs = vec![content_dir.to_path_buf()];
+		while let Some(dir) = dirs.pop() {
+			let abs = root.join(&dir);
+			for entry in read_dir(&abs).map_err(Error::io(&abs))?.filter_map(|res| res.ok()) {
+				let path = dir.join(entry.file_name());
+				match entry.file_type() {
+					Ok(kind) if kind.is_dir() => dirs.push(path),
+					Ok(kind) if kind.is_file() => {
+						let path = path.to_string_lossy().to_string();
+						if filter.matches(&path) {
+							posts.insert(path);
+						}
+					},
+					_ => ()
+				}
+			}
+		}
+		Ok(posts)
+	}
+	/// Adds a post without any history yet.
+	pub fn insert(&mut self, path: String) {
+		let post = BlogPost {
+			path: path.clone(),
+			history: None
+		};
+		self.0.insert(path, post);
+	}
+	pub fn contains(&self, path: &str) -> bool {
+		self.0.contains_key(path)
+	}
+	pub fn iter(&self) -> impl Iterator<Item = &BlogPost> {
+		self.0.values()
+	}
+	pub fn remove(&mut self, path: &str) -> Option<BlogPost> {
+		self.0.remove(path)
+	}
+	pub fn get_mut(&mut self, path: &str) -> Option<&mut BlogPost> {
+		self.0.get_mut(path)
+	}
+	/// Paths of the posts no commit has touched.
+	pub fn untracked(&self) -> Vec<String> {
+		self.0.values().filter(|post| post.history.is_none()).map(|post| post.path.clone()).collect()
+	}
+	/// All posts, highest numeric prefix first. Fails on the first post that
+	/// does not follow the naming convention.
+	pub fn by_prefix(&self) -> Result<Vec<&BlogPost>> {
+		let mut posts = self.0.values().rev()
+			.map(|post| Ok((post.name()?.index, post)))
+			.collect::<Result<Vec<_>>>()?;
+		posts.sort_by_key(|(index, _)| Reverse(*index));
+		Ok(posts.into_iter().map(|(_, post)| post).collect())
+	}
+	/// Walks the history leading up to `head` and records when and by whom
+	/// each post was touched.
+	///
+	/// Every file below `content_dir` is followed across renames, so a
+	/// retitled post keeps the publish date of its original path.
+	pub fn collect_history(&mut self, repo: &Repository, head: &Commit, content_dir: &Path, merges: Merges) -> Result<()> {
+		let mut records = HashMap::new();
+		walk(repo, head, None, content_dir, merges, &mut records)?;
+		self.assign(records);
+		Ok(())
+	}
+	/// Like [`collect_history`](Self::collect_history), but only walks the
+	/// commits added since the run that wrote `cache_path`. The cache is
+	/// rebuilt from scratch if it is missing, was written with different
+	/// settings, or its commit is no longer an ancestor of `head`.
+	pub fn collect_history_cached(&mut self, repo: &Repository, head: &Commit, content_dir: &Path, merges: Merges, cache_path: &Path) -> Result<()> {
+		let (since, mut records) = match cache::load(cache_path, content_dir, merges) {
+			Some((cached, records)) if cached == head.id() || repo.graph_descendant_of(head.id(), cached).unwrap_or(false) => (Some(cached), records),
+			_ => (None, HashMap::new())
+		};
+		walk(repo, head, since, content_dir, merges, &mut records)?;
+		cache::store(cache_path, content_dir, merges, head.id(), &records)?;
+		self.assign(records);
+		Ok(())
+	}
+	fn assign(&mut self, records: HashMap<PathBuf, PostHistory>) {
+		for (path, record) in records {
+			if let Some(post) = self.get_mut(&path.to_string_lossy()) {
+				post.history = Some(record);
+			}
+		}
+	}
+}
+
+/// Walks the commits leading up to `head`, excluding those reachable from
+/// `since`, and updates `records` with every change below `content_dir`.
+fn walk(repo: &Repository, head: &Commit, since: Option<Oid>, content_dir: &Path, merges: Merges, records: &mut HashMap<PathBuf, PostHistory>) -> Result<()> {
+	// Credits to @Shnatsel on GH; https://github.com/rust-lang/git2-rs/issues/588#issuecomment-856757971
+	let mut revwalk = repo.revwalk()?;
+	// Parents have to be seen before their children, even when commit times tie
+	let mut sort = Sort::TOPOLOGICAL;
+	sort.insert(Sort::TIME);
+	sort.insert(Sort::REVERSE);
+	revwalk.set_sorting(sort)?;
+	revwalk.push(head.id())?;
+	if let Some(since) = since {
+		revwalk.hide(since)?;
+	}
+
+	let mut find_opts = DiffFindOptions::new();
+	find_opts.renames(true);
+	// Only the content directory matters, the rest of the repository is never diffed
+	let mut diff_opts = DiffOptions::new();
+	diff_opts.pathspec(content_dir);
+
+	for commit in revwalk.filter_map(|commit| commit.ok()) {
+		let commit = repo.find_commit(commit)?;
+		let parents = commit.parent_count();
+		if parents > 1 && merges == Merges::Skip {
+			continue;
+		}
+		// The root commit is diffed against the empty tree
+		let prev_tree = match parents {
+			0 => None,
+			_ => Some(commit.parent(0)?.tree()?)
+		};
+		let tree = commit.tree()?;
+		if let Some(prev_tree) = &prev_tree {
+			if content_id(prev_tree, content_dir) == content_id(&tree, content_dir) {
+				continue;
+			}
+		}
+		let mut diff = repo.diff_tree_to_tree(prev_tree.as_ref(), Some(&tree), Some(&mut diff_opts))?;
+		diff.find_similar(Some(&mut find_opts))?;
+		for delta in diff.deltas() {
+			let old_path = delta.old_file().path();
+			let introduced = match delta.status() {
+				Delta::Deleted => {
+					if let Some(path) = old_path {
+						records.remove(path);
+					}
+					continue;
+				},
+				Delta::Added | Delta::Copied => true,
+				Delta::Renamed => {
+					if let Some(record) = old_path.and_then(|path| records.remove(path)) {
+						let new_path = delta.new_file().path().unwrap().to_path_buf();
+						// The destination may already carry history, e.g. when a
+						// merge repeats a rename that was walked on its branch
+						let record = match records.remove(&new_path) {
+							Some(existing) => combine(existing, record),
+							None => record
+						};
+						records.insert(new_path, record);
+					}
+					true
+				},
+				_ => false
+			};
+			let path = delta.new_file().path().unwrap();
+			if !path.starts_with(content_dir) {
+				continue;
+			}
+			let time = Time(commit.time());
+			let author = AuthorCtx::from(&commit.author());
+			match records.get_mut(path) {
+				Some(record) => {
+					record.latest = time;
+					// The commits of a merged branch are walked on their own, so
+					// the merge itself does not make its author a contributor
+					if parents <= 1 {
+						add_contributor(record, author);
+					}
+				},
+				// An edit to a path whose history moved on, like a post that was
+				// renamed on a branch merged later, does not start a new post
+				None if !introduced => (),
+				None => {
+					let origin = Some((commit.id(), path.to_string_lossy().to_string()));
+					records.insert(path.to_path_buf(), PostHistory { origin, initial: time, latest: time, author, contributors: Vec::new() });
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Id of the content directory within `tree`, if it exists.
+fn content_id(tree: &Tree, content_dir: &Path) -> Option<Oid> {
+	if content_dir.as_os_str().is_empty() {
+		return Some(tree.id());
+	}
+	tree.get_path(content_dir).ok().map(|entry| entry.id())
+}
+
+/// Adds `author` to the contributors of `record`, unless already credited.
+fn add_contributor(record: &mut PostHistory, author: AuthorCtx) {
+	let known = record.author.email == author.email || record.contributors.iter().any(|contributor| contributor.email == author.email);
+	if !known {
+		record.contributors.push(author);
+	}
+}
+
+/// Merges two records that ended up at the same path, keeping the one that
+/// was introduced first together with the latest update and all authors of both.
+fn combine(a: PostHistory, b: PostHistory) -> PostHistory {
+	let (mut first, second) = if b.initial.seconds() < a.initial.seconds() { (b, a) } else { (a, b) };
+	if second.latest.seconds() > first.latest.seconds() {
+		first.latest = second.latest;
+	}
+	for author in std::iter::once(second.author).chain(second.contributors) {
+		add_contributor(&mut first, author);
+	}
+	first
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use git2::{IndexAddOption, Signature};
+	use tempfile::TempDir;
+
+	/// Midnight of 2021-01-01 plus `day` days.
+	fn day(day: i64) -> i64 {
+		1_609_459_200 + day * 86_400
+	}
+
+	/// Post contents that stay similar enough across edits for rename detection.
+	fn text(edit: &str) -> String {
+		format!("# Post\n\n{}\nline 1\nline 2\nline 3\nline 4\nline 5\nline 6\nline 7\nline 8\n", edit)
+	}
+
+	struct Fixture {
+		dir: TempDir,
+		repo: Repository
+	}
+	impl Fixture {
+		fn new() -> Self {
+			let dir = tempfile::tempdir().unwrap();
+			let repo = Repository::init(dir.path()).unwrap();
+			Self { dir, repo }
+		}
+		/// Commits a tree holding exactly `files` on top of `parents`.
+		fn commit(&self, files: &[(&str, &str)], parents: &[Oid], at: i64, author: &str) -> Oid {
+			let root = self.repo.workdir().unwrap();
+			let _ = std::fs::remove_dir_all(root.join("content"));
+			for (path, contents) in files {
+				let path = root.join(path);
+				std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+				std::fs::write(path, contents).unwrap();
+			}
+			let mut index = self.repo.index().unwrap();
+			index.clear().unwrap();
+			index.add_all(["content"].iter(), IndexAddOption::DEFAULT, None).unwrap();
+			let tree = self.repo.find_tree(index.write_tree().unwrap()).unwrap();
+			let signature = Signature::new(author, &format!("{}@example.org", author.to_lowercase()), &git::Time::new(at, 0)).unwrap();
+			let parents = parents.iter().map(|parent| self.repo.find_commit(*parent).unwrap()).collect::<Vec<_>>();
+			let parents = parents.iter().collect::<Vec<_>>();
+			self.repo.commit(None, &signature, &signature, "commit", &tree, &parents).unwrap()
+		}
+		fn posts(&self, head: Oid) -> (Commit<'_>, BlogPosts) {
+			let head = self.repo.find_commit(head).unwrap();
+			let filter = PostFilter::new(Path::new("content"), &[], &[]).unwrap();
+			let posts = BlogPosts::from_tree(&self.repo, &head.tree().unwrap(), Path::new("content"), &filter).unwrap();
+			(head, posts)
+		}
+		/// `(origin commit, initial, latest, author)` of every post at `head`.
+		fn history(&self, head: Oid) -> BTreeMap<String, (Option<Oid>, i64, i64, String)> {
+			let (head, mut posts) = self.posts(head);
+			posts.collect_history(&self.repo, &head, Path::new("content"), Merges::FirstParent).unwrap();
+			summarize(&posts)
+		}
+	}
+
+	/// Everything collected about the posts at `head`, walking all of history
+	/// or going through the cache at `cache`.
+	fn collected(fixture: &Fixture, head: Oid, cache: Option<&Path>) -> Vec<String> {
+		let (head, mut posts) = fixture.posts(head);
+		match cache {
+			Some(cache) => posts.collect_history_cached(&fixture.repo, &head, Path::new("content"), Merges::FirstParent, cache).unwrap(),
+			None => posts.collect_history(&fixture.repo, &head, Path::new("content"), Merges::FirstParent).unwrap()
+		}
+		posts.iter().map(|post| format!("{} {:?}", post.path(), post.history())).collect()
+	}
+
+	fn summarize(posts: &BlogPosts) -> BTreeMap<String, (Option<Oid>, i64, i64, String)> {
+		posts.iter().map(|post| {
+			let history = post.history().unwrap();
+			let origin = history.origin.as_ref().map(|(commit, _)| *commit);
+			(post.path().to_string(), (origin, history.initial.seconds(), history.latest.seconds(), history.author.name.clone()))
+		}).collect()
+	}
+
+	#[test]
+	fn rename_keeps_publish_date() {
+		let fixture = Fixture::new();
+		let root = fixture.commit(&[("content/001.Old.md", &text(""))], &[], day(0), "Alice");
+		let renamed = fixture.commit(&[("content/001.New.md", &text(""))], &[root], day(1), "Bob");
+		let edited = fixture.commit(&[("content/001.New.md", &text("edit"))], &[renamed], day(2), "Bob");
+		let history = fixture.history(edited);
+		assert_eq!(history.len(), 1);
+		assert_eq!(history["content/001.New.md"], (Some(root), day(0), day(2), "Alice".to_string()));
+	}
+
+	#[test]
+	fn root_commit_posts() {
+		let fixture = Fixture::new();
+		let root = fixture.commit(&[("content/001.First.md", &text("")), ("content/002.Second.md", &text(""))], &[], day(0), "Alice");
+		// Same timestamp as the root, which still has to be walked first
+		let edited = fixture.commit(&[("content/001.First.md", &text("")), ("content/002.Second.md", &text("edit"))], &[root], day(0), "Bob");
+		let history = fixture.history(edited);
+		assert_eq!(history["content/001.First.md"], (Some(root), day(0), day(0), "Alice".to_string()));
+		assert_eq!(history["content/002.Second.md"], (Some(root), day(0), day(0), "Alice".to_string()));
+	}
+
+	#[test]
+	fn merged_post() {
+		let fixture = Fixture::new();
+		let root = fixture.commit(&[("content/001.Hello.md", &text(""))], &[], day(0), "Alice");
+		let branch = fixture.commit(&[("content/001.Hello.md", &text("")), ("content/002.Branch.md", &text(""))], &[root], day(1), "Carol");
+		let main = fixture.commit(&[("content/001.Hello.md", &text("edit"))], &[root], day(2), "Alice");
+		let merge = fixture.commit(&[("content/001.Hello.md", &text("edit")), ("content/002.Branch.md", &text(""))], &[main, branch], day(3), "Alice");
+		let history = fixture.history(merge);
+		assert_eq!(history["content/002.Branch.md"], (Some(branch), day(1), day(3), "Carol".to_string()));
+		assert_eq!(history["content/001.Hello.md"], (Some(root), day(0), day(2), "Alice".to_string()));
+	}
+
+	#[test]
+	fn merged_rename_keeps_publish_date() {
+		let fixture = Fixture::new();
+		let root = fixture.commit(&[("content/001.Old.md", &text(""))], &[], day(0), "Alice");
+		let renamed = fixture.commit(&[("content/001.New.md", &text(""))], &[root], day(31), "Bob");
+		let typo = fixture.commit(&[("content/001.Old.md", &text("typo"))], &[root], day(59), "Carol");
+		let merge = fixture.commit(&[("content/001.New.md", &text("typo"))], &[typo, renamed], day(90), "Alice");
+		let history = fixture.history(merge);
+		assert_eq!(history.len(), 1);
+		assert_eq!(history["content/001.New.md"], (Some(root), day(0), day(90), "Alice".to_string()));
+	}
+
+	#[test]
+	fn cached_history() {
+		let fixture = Fixture::new();
+		let cache = fixture.dir.path().join("cache.json");
+		let root = fixture.commit(&[("content/001.Hello.md", &text(""))], &[], day(0), "Alice");
+		let second = fixture.commit(&[("content/001.Hello.md", &text("")), ("content/002.Second.md", &text(""))], &[root], day(1), "Bob");
+		assert_eq!(collected(&fixture, second, Some(&cache)), collected(&fixture, second, None));
+		assert!(cache.exists());
+
+		// New commits on top of the cached one
+		let renamed = fixture.commit(&[("content/001.Hello.md", &text("edit")), ("content/002.Renamed.md", &text(""))], &[second], day(2), "Carol");
+		let third = fixture.commit(&[("content/001.Hello.md", &text("edit")), ("content/002.Renamed.md", &text("")), ("content/003.Third.md", &text(""))], &[renamed], day(3), "Alice");
+		assert_eq!(collected(&fixture, third, Some(&cache)), collected(&fixture, third, None));
+		assert!(std::fs::read_to_string(&cache).unwrap().contains(&third.to_string()));
+		// Served from the cache alone
+		assert_eq!(collected(&fixture, third, Some(&cache)), collected(&fixture, third, None));
+
+		// A force-push replacing the cached commit
+		let rewritten = fixture.commit(&[("content/001.Hello.md", &text("")), ("content/004.Other.md", &text(""))], &[root], day(4), "Bob");
+		assert_eq!(collected(&fixture, rewritten, Some(&cache)), collected(&fixture, rewritten, None));
+		assert!(collected(&fixture, rewritten, None).iter().all(|post| !post.contains("002.Renamed")));
+	}
+
+	fn name(path: &str) -> Option<(u64, String, bool)> {
+		PostName::parse(path).ok().map(|name| (name.index, name.title, name.draft))
+	}
+
+	#[test]
+	fn post_names() {
+		assert_eq!(name("content/001.Hello.md"), Some((1, "Hello".to_string(), false)));
+		assert_eq!(name("content/2021/10.Hello world.md"), Some((10, "Hello world".to_string(), false)));
+		assert_eq!(name("content/001.v1.2 released.md"), Some((1, "v1.2 released".to_string(), false)));
+		assert_eq!(name("content/002.Upcoming.draft.md"), Some((2, "Upcoming".to_string(), true)));
+	}
+
+	#[test]
+	fn invalid_post_names() {
+		assert_eq!(name("content/README.md"), None);
+		assert_eq!(name("content/001.md"), None);
+		assert_eq!(name("content/001..md"), None);
+		assert_eq!(name("content/.Hello.md"), None);
+		assert_eq!(name("content/v1.Hello.md"), None);
+		assert_eq!(name("content/+1.Hello.md"), None);
+	}
+
+	#[test]
+	fn default_filter() {
+		let filter = PostFilter::new(Path::new("content"), &[], &["content/assets/**".to_string()]).unwrap();
+		assert!(filter.matches("content/001.Hello.md"));
+		assert!(filter.matches("content/2021/001.Hello.md"));
+		assert!(!filter.matches("content/README.md"));
+		assert!(!filter.matches("content/001.Hello.png"));
+		assert!(!filter.matches("content/assets/001.Hello.md"));
+		assert!(!filter.matches("other/001.Hello.md"));
+	}
+}
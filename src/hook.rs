@@ -0,0 +1,139 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Support for running gitfeet from a `post-receive` hook.
+
+use std::str::FromStr;
+
+use git2::{Oid, Repository};
+
+use crate::error::{Error, Result};
+
+/// A single `<old> <new> <ref>` line a `post-receive` hook reads from stdin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefUpdate {
+	pub old: Oid,
+	pub new: Oid,
+	pub name: String
+}
+impl RefUpdate {
+	/// Parses every line of `input`.
+	pub fn parse_all(input: &str) -> Result<Vec<Self>> {
+		input.lines().filter(|line| !line.trim().is_empty()).map(str::parse).collect()
+	}
+	/// Whether the update deleted the ref.
+	pub fn is_deletion(&self) -> bool {
+		self.new.is_zero()
+	}
+}
+impl FromStr for RefUpdate {
+	type Err = Error;
+	fn from_str(s: &str) -> Result<Self> {
+		let mut fields = s.split_whitespace();
+		match (fields.next(), fields.next(), fields.next(), fields.next()) {
+			(Some(old), Some(new), Some(name), None) => Ok(Self {
+				old: Oid::from_str(old)?,
+				new: Oid::from_str(new)?,
+				name: name.to_string()
+			}),
+			_ => Err(Error::InvalidRefUpdate(s.to_string()))
+		}
+	}
+}
+
+/// Resolves `reference` to the full name of the ref a push would update,
+/// e.g. `main` or `HEAD` to `refs/heads/main`.
+pub fn full_ref_name(repo: &Repository, reference: &str) -> Result<String> {
+	if reference == "HEAD" {
+		let head = repo.find_reference("HEAD")?;
+		if let Some(target) = head.symbolic_target() {
+			return Ok(target.to_string());
+		}
+	}
+	if reference.starts_with("refs/") {
+		return Ok(reference.to_string());
+	}
+	match repo.resolve_reference_from_short_name(reference) {
+		Ok(resolved) => Ok(resolved.name().unwrap_or(reference).to_string()),
+		Err(_) => Ok(format!("refs/heads/{}", reference))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const OLD: &str = "1111111111111111111111111111111111111111";
+	const NEW: &str = "2222222222222222222222222222222222222222";
+	const ZERO: &str = "0000000000000000000000000000000000000000";
+
+	#[test]
+	fn parse() {
+		let update: RefUpdate = format!("{} {} refs/heads/main", OLD, NEW).parse().unwrap();
+		assert_eq!(update.old, Oid::from_str(OLD).unwrap());
+		assert_eq!(update.new, Oid::from_str(NEW).unwrap());
+		assert_eq!(update.name, "refs/heads/main");
+		assert!(!update.is_deletion());
+	}
+
+	#[test]
+	fn deletion_and_creation() {
+		let deleted: RefUpdate = format!("{} {} refs/heads/main", OLD, ZERO).parse().unwrap();
+		assert!(deleted.is_deletion());
+		let created: RefUpdate = format!("{} {} refs/heads/main", ZERO, NEW).parse().unwrap();
+		assert!(created.old.is_zero());
+		assert!(!created.is_deletion());
+	}
+
+	#[test]
+	fn malformed() {
+		assert!(format!("{} {}", OLD, NEW).parse::<RefUpdate>().is_err());
+		assert!(format!("{} {} refs/heads/main extra", OLD, NEW).parse::<RefUpdate>().is_err());
+		assert!(format!("{} nothex refs/heads/main", OLD).parse::<RefUpdate>().is_err());
+	}
+
+	#[test]
+	fn parse_all_skips_blank_lines() {
+		let input = format!("\n{} {} refs/heads/main\n  \n{} {} refs/tags/v1\n", OLD, NEW, ZERO, NEW);
+		let updates = RefUpdate::parse_all(&input).unwrap();
+		assert_eq!(updates.len(), 2);
+		assert_eq!(updates[1].name, "refs/tags/v1");
+		assert!(RefUpdate::parse_all(&format!("{} {} a b\n", OLD, NEW)).is_err());
+	}
+
+	#[test]
+	fn full_ref_names() {
+		let dir = tempfile::tempdir().unwrap();
+		let repo = Repository::init_bare(dir.path()).unwrap();
+		repo.set_head("refs/heads/main").unwrap();
+		// HEAD of a bare repository resolves to its branch, even before the first push
+		assert_eq!(full_ref_name(&repo, "HEAD").unwrap(), "refs/heads/main");
+
+		let tree = repo.treebuilder(None).unwrap().write().unwrap();
+		let tree = repo.find_tree(tree).unwrap();
+		let signature = git2::Signature::now("Alice", "alice@example.org").unwrap();
+		let commit = repo.commit(Some("refs/heads/main"), &signature, &signature, "init", &tree, &[]).unwrap();
+		repo.reference("refs/tags/v1", commit, false, "tag").unwrap();
+		assert_eq!(full_ref_name(&repo, "HEAD").unwrap(), "refs/heads/main");
+		assert_eq!(full_ref_name(&repo, "main").unwrap(), "refs/heads/main");
+		assert_eq!(full_ref_name(&repo, "v1").unwrap(), "refs/tags/v1");
+		assert_eq!(full_ref_name(&repo, "refs/heads/other").unwrap(), "refs/heads/other");
+		assert_eq!(full_ref_name(&repo, "other").unwrap(), "refs/heads/other");
+	}
+}
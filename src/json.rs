@@ -0,0 +1,111 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! [JSON Feed 1.1](https://www.jsonfeed.org/version/1.1/) serialization of a [`Context`].
+
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::feed::{AuthorCtx, Context, EntryCtx};
+
+const VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+#[derive(Serialize)]
+struct JsonAuthor<'c> {
+	name: &'c str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	url: Option<&'c str>
+}
+impl<'c> From<&'c AuthorCtx> for JsonAuthor<'c> {
+	fn from(author: &'c AuthorCtx) -> Self {
+		Self {
+			name: &author.name,
+			url: author.uri.as_deref()
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct JsonItem<'c> {
+	id: &'c str,
+	url: &'c str,
+	title: &'c str,
+	content_html: &'c str,
+	date_published: &'c str,
+	date_modified: &'c str,
+	authors: Vec<JsonAuthor<'c>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	summary: Option<&'c str>,
+	#[serde(skip_serializing_if = "<[_]>::is_empty")]
+	tags: &'c [String],
+	#[serde(skip_serializing_if = "Option::is_none")]
+	language: Option<&'c str>
+}
+impl<'c> From<&'c EntryCtx> for JsonItem<'c> {
+	fn from(entry: &'c EntryCtx) -> Self {
+		Self {
+			id: &entry.id,
+			url: &entry.link,
+			title: &entry.title,
+			content_html: &entry.content,
+			date_published: &entry.published,
+			date_modified: &entry.updated,
+			authors: entry.authors.iter().map(JsonAuthor::from).collect(),
+			summary: entry.summary.as_deref(),
+			tags: &entry.tags,
+			language: entry.lang.as_deref()
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct JsonFeed<'c> {
+	version: &'static str,
+	title: &'c str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	home_page_url: Option<&'c str>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	feed_url: Option<&'c str>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	description: Option<&'c str>,
+	// Atom's logo is the larger image, its icon the favicon-sized one
+	#[serde(skip_serializing_if = "Option::is_none")]
+	icon: Option<&'c str>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	favicon: Option<&'c str>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	authors: Vec<JsonAuthor<'c>>,
+	items: Vec<JsonItem<'c>>
+}
+
+pub(crate) fn render(ctx: &Context) -> Result<String> {
+	let feed = JsonFeed {
+		version: VERSION,
+		// JSON Feed requires a title, FeedBuilder always sets one
+		title: ctx.feed.title.as_deref().unwrap_or(&ctx.base_url),
+		home_page_url: ctx.feed.alternate_link.as_deref(),
+		feed_url: ctx.feed.self_link.as_deref(),
+		description: ctx.feed.subtitle.as_deref(),
+		icon: ctx.feed.logo.as_deref(),
+		favicon: ctx.feed.icon.as_deref(),
+		authors: ctx.feed.author.iter().map(JsonAuthor::from).collect(),
+		items: ctx.entries.iter().map(JsonItem::from).collect()
+	};
+	Ok(serde_json::to_string_pretty(&feed)?)
+}
@@ -0,0 +1,56 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Library interface of gitfeet.
+//!
+//! Collects the git history of the posts in a vueBlog compatible repository
+//! and renders them into a feed:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let repo = git2::Repository::open(".")?;
+//! let feed = gitfeet::FeedBuilder::new(&repo)
+//!     .content_dir("content/")
+//!     .base_url("https://example.org/read/")
+//!     .max_entries(10)
+//!     .build()?;
+//! println!("{}", feed.render(&std::fs::read_to_string("feed.xml.in")?)?);
+//! # Ok(())
+//! # }
+//! ```
+
+macro_rules! crate_version {
+    () => {
+        env!("CARGO_PKG_VERSION")
+    };
+}
+
+mod cache;
+pub mod config;
+mod error;
+mod feed;
+mod frontmatter;
+mod history;
+pub mod hook;
+mod json;
+pub mod output;
+
+pub use error::{Error, Result};
+pub use feed::{AuthorCtx, AuthorInfo, Context, EntryCtx, FeedBuilder, FeedMeta, Format, IdScheme, Order, TitleStyle, Untracked, ATOM_TEMPLATE, RSS_TEMPLATE};
+pub use history::{BlogPost, BlogPosts, Merges, PostFilter, PostHistory, PostName, Time};
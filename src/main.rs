@@ -17,176 +17,292 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::mem::MaybeUninit;
-use std::collections::BTreeMap;
-use std::fs::read_dir;
-use std::fmt;
-
-use anyhow::Result;
-use chrono::{DateTime, TimeZone, FixedOffset, Utc};
-use git2::{self as git, Repository, Sort};
-use pulldown_cmark::{Parser, Options as MdO, html};
-use serde::Serialize;
-use tinytemplate::TinyTemplate;
-
-macro_rules! crate_version {
-    () => {
-        env!("CARGO_PKG_VERSION")
-    };
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context as _, Result};
+use chrono::DateTime;
+use clap::{crate_version, App, Arg};
+use git2::{ErrorCode, Repository};
+
+use gitfeet::config::{self, Config};
+use gitfeet::hook::{self, RefUpdate};
+use gitfeet::{output, FeedBuilder, Format};
+
+/// A feed to render; `path` is `None` when writing to stdout.
+struct Output {
+	format: Format,
+	path: Option<PathBuf>,
+	template: Option<PathBuf>
 }
 
-#[derive(Clone, Copy)]
-struct Time(git::Time);
-impl Time {
-	fn to_chrono(&self) -> DateTime<FixedOffset> {
-		FixedOffset::east(self.0.offset_minutes() * 60).timestamp(self.0.seconds(), 0)
+/// Parses a `--output` value of the form `[FORMAT:]PATH`, where `-` stands for stdout.
+fn parse_output(value: &str, default_format: Format, template: Option<&PathBuf>) -> Output {
+	let (format, path) = match value.split_once(':').and_then(|(format, path)| Some((format.parse().ok()?, path))) {
+		Some((format, path)) => (format, path),
+		None => (default_format, value)
+	};
+	Output {
+		format,
+		path: Some(PathBuf::from(path)).filter(|path| path.as_os_str() != "-"),
+		template: template.filter(|_| format == default_format).cloned()
 	}
 }
-impl fmt::Debug for Time {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		f.write_fmt(format_args!("Time {}", self.0.seconds()))
+
+/// Reads `name` from the repository root: the working tree if there is one,
+/// otherwise the tree of `reference`.
+fn read_repo_file(repo: &Repository, reference: &str, name: &str) -> Result<Option<String>> {
+	match repo.workdir() {
+		Some(root) if root.join(name).is_file() => Ok(Some(std::fs::read_to_string(root.join(name))
+			.with_context(|| format!("failed to read {}", name))?)),
+		Some(_) => Ok(None),
+		None => {
+			let tree = repo.revparse_single(reference)?.peel_to_tree()?;
+			match tree.get_path(Path::new(name)) {
+				Ok(entry) => {
+					let blob = entry.to_object(repo)?.peel_to_blob()?;
+					Ok(Some(String::from_utf8_lossy(blob.content()).into_owned()))
+				},
+				Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+				Err(err) => Err(err.into())
+			}
+		}
 	}
 }
 
-#[derive(Debug)]
-struct BlogPost<'n> {
-	path: &'n str,
-	initial: Option<Time>,
-	latest: MaybeUninit<Time>,
-	author: MaybeUninit<(Option<String>, Option<String>)>
+/// Reads the template for `format`. An explicitly configured template has
+/// to exist, the conventional `feed.xml.in` is optional.
+fn load_template(format: Format, path: Option<&Path>, conventional: Option<&str>) -> Result<Option<String>> {
+	if path.is_some() && format.template().is_none() {
+		bail!("{} feeds are not rendered through a template", format);
+	}
+	Ok(match path {
+		Some(path) => Some(std::fs::read_to_string(path)
+			.with_context(|| format!("failed to read template {}", path.display()))?),
+		None if format == Format::Atom => conventional.map(str::to_string),
+		None => None
+	})
 }
 
-#[derive(Debug)]
-struct BlogPosts<'p> (BTreeMap<&'p str, BlogPost<'p>>);
-impl <'n> BlogPosts<'n> {
-	fn new() -> Self {
-		Self(BTreeMap::new())
-	}
-	fn insert_uninit(&mut self, path: &'n str) {
-		let post = BlogPost {
-			path: &path,
-			initial: None,
-			latest: MaybeUninit::uninit(),
-			author: MaybeUninit::uninit()
-		};
-		self.0.insert(&post.path, post);
+fn main() -> Result<()> {
+	let matches = App::new("gitfeet")
+		.version(crate_version!())
+		.about("A Atom feed generator for vueBlog compatible repositories")
+		.arg(Arg::with_name("repo")
+			.short("r")
+			.long("repo")
+			.value_name("PATH")
+			.default_value(".")
+			.help("Path to the blog repository"))
+		.arg(Arg::with_name("config")
+			.long("config")
+			.value_name("FILE")
+			.help("Configuration file [default: gitfeet.toml in the repository root, if present; read from --ref in bare repositories]"))
+		.arg(Arg::with_name("content")
+			.short("c")
+			.long("content")
+			.value_name("DIR")
+			.help("Directory containing the posts, relative to the repository root [default: content/]"))
+		.arg(Arg::with_name("include")
+			.long("include")
+			.value_name("GLOB")
+			.multiple(true)
+			.number_of_values(1)
+			.help("Only treat files matching GLOB, relative to the repository root, as posts; may be repeated [default: CONTENT/**/[0-9]*.*.md]"))
+		.arg(Arg::with_name("exclude")
+			.long("exclude")
+			.value_name("GLOB")
+			.multiple(true)
+			.number_of_values(1)
+			.help("Never treat files matching GLOB as posts; may be repeated"))
+		.arg(Arg::with_name("format")
+			.short("f")
+			.long("format")
+			.value_name("FORMAT")
+			.possible_values(&["atom", "rss", "json"])
+			.help("Feed format of outputs without a FORMAT: prefix [default: atom]"))
+		.arg(Arg::with_name("template")
+			.short("t")
+			.long("template")
+			.value_name("FILE")
+			.help("Template for the feeds in --format [default: feed.xml.in in the repository root for Atom, otherwise the built-in one]"))
+		.arg(Arg::with_name("output")
+			.short("o")
+			.long("output")
+			.value_name("[FORMAT:]FILE")
+			.multiple(true)
+			.number_of_values(1)
+			.help("Write a feed to FILE instead of stdout; may be repeated to emit several formats"))
+		.arg(Arg::with_name("skip-unchanged")
+			.long("skip-unchanged")
+			.help("Leave output files untouched if only the feed's updated timestamp would change"))
+		.arg(Arg::with_name("max-entries")
+			.short("n")
+			.long("max-entries")
+			.value_name("N")
+			.help("Maximum number of entries in the feed [default: 20]"))
+		.arg(Arg::with_name("order")
+			.long("order")
+			.value_name("ORDER")
+			.possible_values(&["published", "updated", "prefix"])
+			.help("Keep the entries published or updated last, or those with the highest file name prefix [default: published]"))
+		.arg(Arg::with_name("base-url")
+			.short("b")
+			.long("base-url")
+			.value_name("URL")
+			.help("Prefix for entry ids and links [default: https://sp1rit.ml/read/]"))
+		.arg(Arg::with_name("id-scheme")
+			.long("id-scheme")
+			.value_name("SCHEME")
+			.possible_values(&["blob", "tag"])
+			.help("Derive entry ids from the current blob or as tag: URI from the commit introducing the post [default: blob]"))
+		.arg(Arg::with_name("merges")
+			.long("merges")
+			.value_name("MODE")
+			.possible_values(&["first-parent", "skip"])
+			.help("Diff merge commits against their first parent or ignore them [default: first-parent]"))
+		.arg(Arg::with_name("untracked")
+			.long("untracked")
+			.value_name("POLICY")
+			.possible_values(&["skip", "warn", "mtime"])
+			.help("How to treat posts that were never committed [default: warn]"))
+		.arg(Arg::with_name("title-style")
+			.long("title-style")
+			.value_name("STYLE")
+			.possible_values(&["verbatim", "underscores", "slug"])
+			.help("Turn underscores, or underscores and hyphens, in file names into spaces [default: verbatim]"))
+		.arg(Arg::with_name("now")
+			.long("now")
+			.value_name("TIME")
+			.help("Generate the feed as of TIME (RFC 3339), leaving out posts scheduled after it [default: current time]"))
+		.arg(Arg::with_name("cache")
+			.long("cache")
+			.value_name("FILE")
+			.help("Keep the collected history in FILE so later runs only walk new commits"))
+		.arg(Arg::with_name("ref")
+			.long("ref")
+			.value_name("REF")
+			.help("Branch, tag or commit to generate the feed from [default: HEAD]"))
+		.arg(Arg::with_name("hook")
+			.long("hook")
+			.help("Run as post-receive hook: read `<old> <new> <ref>` lines from stdin and only regenerate the feeds if --ref was pushed and posts changed"))
+		.get_matches();
+
+	let repo = Repository::open(matches.value_of("repo").unwrap())?;
+	// Relative paths are resolved against the working tree, or the git
+	// directory of bare repositories
+	let root = repo.workdir().unwrap_or_else(|| repo.path()).to_path_buf();
+
+	let config = match matches.value_of("config") {
+		Some(path) => Config::load(Path::new(path))?,
+		None => match read_repo_file(&repo, matches.value_of("ref").unwrap_or("HEAD"), config::FILE_NAME)? {
+			Some(raw) => Config::parse(&raw, Path::new(config::FILE_NAME))?,
+			None => Config::default()
+		}
+	};
+	let reference = matches.value_of("ref").or(config.reference.as_deref()).unwrap_or("HEAD").to_string();
+
+	let mut builder = config.apply(FeedBuilder::new(&repo));
+	if let Some(n) = matches.value_of("max-entries") {
+		builder = builder.max_entries(n.parse().context("--max-entries must be a positive integer")?);
 	}
-	fn get_mut(&mut self, path: &str) -> Option<&mut BlogPost<'n>> {
-		self.0.get_mut(path)
+	if let Some(order) = matches.value_of("order") {
+		builder = builder.order(order.parse()?);
 	}
-	fn get_n_latest(&self, n: usize) -> Vec<&BlogPost> {
-		self.0.iter().rev().take(n).map(|(_k, v)| v).collect()
+	if let Some(base_url) = matches.value_of("base-url") {
+		builder = builder.base_url(base_url);
 	}
-}
+	if let Some(content) = matches.value_of("content") {
+		builder = builder.content_dir(content);
+	}
+	if let Some(include) = matches.values_of("include") {
+		builder = builder.include(include);
+	}
+	if let Some(exclude) = matches.values_of("exclude") {
+		builder = builder.exclude(exclude);
+	}
+	if let Some(id_scheme) = matches.value_of("id-scheme") {
+		builder = builder.id_scheme(id_scheme.parse()?);
+	}
+	if let Some(merges) = matches.value_of("merges") {
+		builder = builder.merges(merges.parse()?);
+	}
+	if let Some(untracked) = matches.value_of("untracked") {
+		builder = builder.untracked(untracked.parse()?);
+	}
+	if let Some(style) = matches.value_of("title-style") {
+		builder = builder.title_style(style.parse()?);
+	}
+	if let Some(now) = matches.value_of("now") {
+		builder = builder.now(DateTime::parse_from_rfc3339(now).context("--now must be a RFC 3339 timestamp")?);
+	}
+	if let Some(cache) = matches.value_of("cache").map(PathBuf::from).or_else(|| config.cache.as_ref().map(|path| root.join(path))) {
+		builder = builder.cache(cache);
+	}
+	builder = builder.reference(&reference);
+	let format = match matches.value_of("format") {
+		Some(format) => format.parse()?,
+		None => config.format.unwrap_or(Format::Atom)
+	};
+	let template_path = matches.value_of("template").map(PathBuf::from)
+		.or_else(|| config.template.as_ref().map(|path| root.join(path)));
+	let outputs: Vec<Output> = match matches.values_of("output") {
+		Some(values) => values.map(|value| parse_output(value, format, template_path.as_ref())).collect(),
+		None if !config.outputs.is_empty() => config.outputs.iter().map(|output| Output {
+			format: output.format,
+			path: Some(root.join(&output.path)),
+			template: output.template.as_ref().map(|path| root.join(path))
+		}).collect(),
+		None => vec![Output {
+			format,
+			path: config.output.as_ref().map(|path| root.join(path)),
+			template: template_path
+		}]
+	};
 
-#[derive(Debug, Serialize)]
-struct AuthorCtx {
-	name: String,
-	email: String
-}
+	let skip_unchanged = matches.is_present("skip-unchanged") || config.skip_unchanged;
 
-#[derive(Debug, Serialize)]
-struct EntryCtx {
-	id: String,
-	title: String,
-	updated: String,
-	author: AuthorCtx,
-	content: String,
-	link: String,
-	published: String
-}
+	if matches.is_present("hook") {
+		if outputs.iter().any(|out| out.path.is_none()) {
+			bail!("hook mode needs an output path for every feed");
+		}
+		let mut input = String::new();
+		std::io::stdin().read_to_string(&mut input).context("failed to read ref updates from stdin")?;
+		let branch = hook::full_ref_name(&repo, &reference)?;
+		let update = RefUpdate::parse_all(&input)?.into_iter().find(|update| update.name == branch);
+		match update {
+			Some(update) if !update.is_deletion() && builder.content_changed(update.old, update.new)? => {
+				builder = builder.reference(update.new.to_string());
+			},
+			_ => return Ok(())
+		}
+	}
 
-#[derive(Debug, Serialize)]
-struct Context {
-	updated: String,
-    gfversion: String,
-	entries: Vec<EntryCtx>
-}
+	// Walk the history and render the markdown once for all outputs
+	let ctx = builder.build()?;
+	for path in &ctx.skipped {
+		eprintln!("warning: skipping {}, it has not been committed yet", path);
+	}
+	let conventional_template = read_repo_file(&repo, &reference, "feed.xml.in")?;
 
-fn main() -> Result<()> {
-	let mut posts = BlogPosts::new();
-	let owned_paths: Vec<String> = read_dir("content/")?.filter_map(|res| res.map(|entry| entry.path().to_string_lossy().to_string()).ok()).collect();
-
-	owned_paths.iter().for_each(|path| posts.insert_uninit(path));
-
-    // Credits to @Shnatsel on GH; https://github.com/rust-lang/git2-rs/issues/588#issuecomment-856757971
-	let repo = Repository::open(".")?;
-	let mut revwalk = repo.revwalk()?;
-	let mut sort = Sort::TIME;
-	sort.insert(Sort::REVERSE);
-	revwalk.set_sorting(sort)?;
-	revwalk.push_head()?;
-
-	for commit in revwalk.filter_map(|commit| commit.ok()) {
-		let commit = repo.find_commit(commit)?;
-		if commit.parent_count() == 1 {
-			let prev_commit = commit.parent(0)?;
-			let tree = commit.tree()?;
-			let prev_tree = prev_commit.tree()?;
-			let diff = repo.diff_tree_to_tree(Some(&prev_tree), Some(&tree), None)?;
-			for delta in diff.deltas() {
-				let path = delta.new_file().path().unwrap();
-				if let Some(post) = posts.get_mut(&path.to_string_lossy()) {
-					let time = Time(commit.time());
-					let author = commit.author();
-					post.initial.get_or_insert(time);
-					unsafe { 
-						post.latest.as_mut_ptr().write(time);
-						post.author.as_mut_ptr().write((author.name().map(|name| name.to_owned()), author.email().map(|mail| mail.to_owned())));
-					}
+	for out in outputs {
+		let template = load_template(out.format, out.template.as_deref(), conventional_template.as_deref())?;
+		let path = match out.path {
+			Some(path) => path,
+			None => {
+				println!("{}", ctx.render_as(out.format, template.as_deref())?);
+				continue;
+			}
+		};
+		if skip_unchanged {
+			if let Ok(existing) = std::fs::read_to_string(&path) {
+				if output::is_unchanged(&ctx, out.format, template.as_deref(), &existing)? {
+					continue;
 				}
 			}
 		}
+		output::write_atomic(&path, &ctx.render_as(out.format, template.as_deref())?)?;
 	}
 
-
-	let posts = posts.get_n_latest(20);
-	
-	let current = repo.head()?.peel_to_tree()?;
-	
-	let mut opts = MdO::empty();
-	opts.insert(MdO::ENABLE_TABLES);
-	opts.insert(MdO::ENABLE_FOOTNOTES);
-	opts.insert(MdO::ENABLE_STRIKETHROUGH);
-	opts.insert(MdO::ENABLE_TASKLISTS);
-	
-	let entries: Vec<EntryCtx> = posts.into_iter().map(|post| {
-		let path = std::path::Path::new(post.path);
-		let oid = current.get_path(path).unwrap().id();
-		let (name, email) = unsafe { &*post.author.as_ptr() };
-		
-		let file_content = std::fs::read_to_string(path).unwrap();
-		let parser = Parser::new_ext(&file_content, opts);
-		let mut content = String::new();
-		html::push_html(&mut content, parser);
-		
-		EntryCtx {
-			id: format!("https://sp1rit.ml/read/{}", oid),
-			title: path.file_stem().unwrap().to_string_lossy().split('.').collect::<Vec<&str>>()[1].to_string(),
-			updated: unsafe { &*post.latest.as_ptr() }.to_chrono().to_rfc3339(),
-			author: AuthorCtx {
-				name: name.as_ref().unwrap().to_string(),
-				email: email.as_ref().unwrap().to_string()
-			},
-			content,
-			link: format!("https://sp1rit.ml/read/{}", oid),
-			published: post.initial.unwrap().to_chrono().to_rfc3339()
-		}
-	}).collect();
-	
-	let template_content = std::fs::read_to_string("feed.xml.in")?;
-	let mut tt = TinyTemplate::new();
-	tt.add_template("feed", &template_content)?;
-	
-	let ctx = Context {
-		updated: Utc::now().to_rfc3339(),
-        gfversion: crate_version!().to_string(),
-		entries
-	};
-	
-	let output = tt.render("feed", &ctx)?;
-	
-	println!("{}", output);
-	
 	Ok(())
 }
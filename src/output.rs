@@ -0,0 +1,223 @@
+/*
+ * gitfeet - atom feed generator for vueBlog compatible repos
+ *
+ * Copyright (C) 2021 Florian "sp1rit" <sp1ritCS@protonmail.com>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ * 
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Writing rendered feeds to disk.
+
+use std::io::Write;
+use std::path::Path;
+
+use chrono::DateTime;
+use tempfile::Builder;
+
+use crate::error::{Error, Result};
+use crate::feed::{Context, Format};
+
+/// Writes `contents` to `path` by way of a temporary file in the same
+/// directory, so a failed run never leaves a truncated feed behind.
+///
+/// The file keeps the permissions of the one it replaces, new files get
+/// the usual `0666` minus umask rather than the private mode of temporary files.
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+	let dir = match path.parent() {
+		Some(dir) if !dir.as_os_str().is_empty() => dir,
+		_ => Path::new(".")
+	};
+	let mut builder = Builder::new();
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		builder.permissions(std::fs::Permissions::from_mode(0o666));
+	}
+	let mut file = builder.tempfile_in(dir).map_err(Error::io(dir))?;
+	if let Ok(meta) = std::fs::metadata(path) {
+		file.as_file().set_permissions(meta.permissions()).map_err(Error::io(file.path()))?;
+	}
+	file.write_all(contents.as_bytes()).map_err(Error::io(file.path()))?;
+	file.as_file().sync_all().map_err(Error::io(file.path()))?;
+	file.persist(path).map_err(|err| Error::io(path)(err.error))?;
+	Ok(())
+}
+
+// Two timestamps whose representations have the same length in every
+// format, so renders using them line up byte for byte.
+const PROBE_A: &str = "2000-01-10T10:10:10+00:00";
+const PROBE_B: &str = "2000-01-20T20:20:20+00:00";
+
+/// The ways templates can render a timestamp.
+#[derive(Clone, Copy)]
+enum Stamp {
+	Rfc3339,
+	Rfc2822
+}
+impl Stamp {
+	const ALL: [Self; 2] = [Self::Rfc3339, Self::Rfc2822];
+
+	fn render(self, probe: &str) -> String {
+		let date = DateTime::parse_from_rfc3339(probe).unwrap();
+		match self {
+			Self::Rfc3339 => date.to_rfc3339(),
+			Self::Rfc2822 => date.to_rfc2822()
+		}
+	}
+	/// Length of the longest timestamp of this kind at the start of `text`
+	/// that is followed by `next`, or by nothing at all if `last`.
+	fn find(self, text: &[u8], next: &[u8], last: bool) -> Option<usize> {
+		let run = text.iter().take_while(|c| c.is_ascii_alphanumeric() || b"-:+., ".contains(c)).count();
+		(1..=run).rev().find(|&len| {
+			let parses = std::str::from_utf8(&text[..len]).ok().is_some_and(|stamp| match self {
+				Self::Rfc3339 => DateTime::parse_from_rfc3339(stamp).is_ok(),
+				Self::Rfc2822 => DateTime::parse_from_rfc2822(stamp).is_ok()
+			});
+			let rest = &text[len..];
+			parses && if last { rest == next } else { rest.starts_with(next) }
+		})
+	}
+}
+
+/// Checks whether `existing` is what `ctx` renders to as `format`, ignoring
+/// the feed's own `updated` timestamp.
+///
+/// The feed is rendered with two different timestamps to locate where
+/// `updated` ends up; there `existing` may contain any timestamp of the same
+/// kind, everything else has to match exactly.
+pub fn is_unchanged(ctx: &Context, format: Format, template: Option<&str>, existing: &str) -> Result<bool> {
+	let mut probe = ctx.clone();
+	probe.updated = PROBE_A.to_string();
+	let a = probe.render_as(format, template)?;
+	probe.updated = PROBE_B.to_string();
+	let b = probe.render_as(format, template)?;
+	let (a, b) = (a.as_bytes(), b.as_bytes());
+	if a.len() != b.len() {
+		return Ok(false);
+	}
+
+	// Split the render into the literal pieces between timestamps
+	let stamps: Vec<_> = Stamp::ALL.iter().map(|stamp| (*stamp, stamp.render(PROBE_A), stamp.render(PROBE_B))).collect();
+	let mut literals = Vec::new();
+	let mut holes = Vec::new();
+	let mut start = 0;
+	let mut i = 0;
+	while i < a.len() {
+		if a[i] == b[i] {
+			i += 1;
+			continue;
+		}
+		// The first differing byte may lie within the timestamp
+		let hole = (start..=i).rev().find_map(|at| stamps.iter()
+			.find(|(_, in_a, in_b)| a[at..].starts_with(in_a.as_bytes()) && b[at..].starts_with(in_b.as_bytes()) && at + in_a.len() > i)
+			.map(|(stamp, in_a, _)| (at, *stamp, in_a.len())));
+		let (at, stamp, len) = match hole {
+			Some(hole) => hole,
+			// Something other than the timestamp depends on it
+			None => return Ok(false)
+		};
+		literals.push(&a[start..at]);
+		holes.push(stamp);
+		start = at + len;
+		i = start;
+	}
+	literals.push(&a[start..]);
+
+	let existing = existing.as_bytes();
+	if !existing.starts_with(literals[0]) {
+		return Ok(false);
+	}
+	let mut pos = literals[0].len();
+	for (i, (stamp, literal)) in holes.iter().zip(&literals[1..]).enumerate() {
+		match stamp.find(&existing[pos..], literal, i + 1 == holes.len()) {
+			Some(len) => pos += len + literal.len(),
+			None => return Ok(false)
+		}
+	}
+	Ok(pos == existing.len())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::feed::{AuthorCtx, EntryCtx, FeedMeta};
+
+	fn context(updated: &str) -> Context {
+		let author = AuthorCtx { name: "Alice".to_string(), email: "alice@example.org".to_string(), uri: None };
+		Context {
+			updated: updated.to_string(),
+			gfversion: "0.1.0".to_string(),
+			base_url: "https://example.org/read/".to_string(),
+			feed: FeedMeta {
+				title: Some("Example".to_string()),
+				self_link: Some("https://example.org/feed.xml".to_string()),
+				..FeedMeta::default()
+			},
+			entries: vec![EntryCtx {
+				id: "https://example.org/read/1".to_string(),
+				title: "Hello".to_string(),
+				updated: "2021-08-02T12:00:00+02:00".to_string(),
+				author: author.clone(),
+				authors: vec![author],
+				contributors: Vec::new(),
+				content: "<p>hello</p>".to_string(),
+				link: "https://example.org/read/1".to_string(),
+				published: "2021-08-01T12:00:00+02:00".to_string(),
+				summary: None,
+				tags: Vec::new(),
+				lang: None
+			}],
+			skipped: Vec::new()
+		}
+	}
+
+	fn check(format: Format, template: Option<&str>) {
+		let old = context("2021-08-03T09:08:07.123456789+00:00").render_as(format, template).unwrap();
+		let now = context("2026-10-16T22:15:56+00:00");
+		assert!(is_unchanged(&now, format, template, &old).unwrap());
+
+		let mut edited = now.clone();
+		edited.entries[0].title = "Hello, world".to_string();
+		assert!(!is_unchanged(&edited, format, template, &old).unwrap());
+		assert!(!is_unchanged(&now, format, template, &format!("{} ", old)).unwrap());
+	}
+
+	#[test]
+	fn atom() {
+		check(Format::Atom, None);
+	}
+
+	#[test]
+	fn rss() {
+		check(Format::Rss, None);
+	}
+
+	#[test]
+	fn json() {
+		check(Format::Json, None);
+	}
+
+	#[test]
+	fn custom_template() {
+		let template = "Generated on {updated} ({updated | rfc2822}), {{ for entry in entries }}{entry.title}{{ endfor }}";
+		check(Format::Atom, Some(template));
+
+		// Words next to the timestamp are not part of it
+		let old = context("2021-08-03T09:08:07+00:00").render_as(Format::Atom, Some(template)).unwrap();
+		let changed = "Regenerated on {updated} ({updated | rfc2822}), {{ for entry in entries }}{entry.title}{{ endfor }}";
+		assert!(!is_unchanged(&context("2026-10-16T22:15:56+00:00"), Format::Atom, Some(changed), &old).unwrap());
+		let changed = "Generated on {updated} at ({updated | rfc2822}), {{ for entry in entries }}{entry.title}{{ endfor }}";
+		assert!(!is_unchanged(&context("2026-10-16T22:15:56+00:00"), Format::Atom, Some(changed), &old).unwrap());
+	}
+}